@@ -1,7 +1,19 @@
 #pragma once
 
+#include <string>
+#include <string_view>
+
 namespace foxglove {
 
 const char* WebSocketUserAgent();
 
+// Compares two strings in time that does not depend on their contents, only on their lengths.
+// Use this for secrets such as authentication tokens
+bool ConstantTimeEquals(std::string_view a, std::string_view b);
+
+// Returns the authentication token given either directly or as the path of a file containing it.
+// Trailing whitespace is stripped from the file's contents. Throws if both are set, or if the file
+// cannot be read or is empty
+std::string ResolveAuthToken(const std::string& token, const std::string& tokenFile);
+
 }  // namespace foxglove
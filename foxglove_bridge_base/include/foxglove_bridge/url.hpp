@@ -0,0 +1,86 @@
+#pragma once
+
+#include <optional>
+#include <string>
+#include <string_view>
+
+namespace foxglove {
+
+namespace detail {
+
+// Calls fn(valueStart, valueEnd) with the bounds of each value of the given query parameter in a
+// request resource such as "/?token=abc&foo=bar", until fn returns false
+template <typename Fn>
+void ForEachQueryParameterValue(std::string_view resource, std::string_view name, Fn&& fn) {
+  auto pos = resource.find('?');
+  while (pos != std::string_view::npos) {
+    const auto start = pos + 1;
+    pos = resource.find('&', start);
+    const auto end = pos == std::string_view::npos ? resource.size() : pos;
+    if (end - start > name.size() && resource.compare(start, name.size(), name) == 0 &&
+        resource[start + name.size()] == '=') {
+      if (!fn(start + name.size() + 1, end)) {
+        return;
+      }
+    }
+  }
+}
+
+}  // namespace detail
+
+// Decodes %XX escape sequences. Malformed sequences are kept as-is
+inline std::string PercentDecode(std::string_view str) {
+  const auto hexValue = [](char c) -> int {
+    if (c >= '0' && c <= '9') {
+      return c - '0';
+    } else if (c >= 'a' && c <= 'f') {
+      return c - 'a' + 10;
+    } else if (c >= 'A' && c <= 'F') {
+      return c - 'A' + 10;
+    }
+    return -1;
+  };
+
+  std::string result;
+  result.reserve(str.size());
+  for (size_t i = 0; i < str.size(); ++i) {
+    if (str[i] == '%' && i + 2 < str.size() && hexValue(str[i + 1]) >= 0 &&
+        hexValue(str[i + 2]) >= 0) {
+      result.push_back(static_cast<char>(hexValue(str[i + 1]) * 16 + hexValue(str[i + 2])));
+      i += 2;
+    } else {
+      result.push_back(str[i]);
+    }
+  }
+  return result;
+}
+
+// Returns the (still percent-encoded) value of a query parameter of a request resource such as
+// "/?token=abc&foo=bar", or std::nullopt if the parameter is not present
+inline std::optional<std::string> GetQueryParameter(std::string_view resource,
+                                                    std::string_view name) {
+  std::optional<std::string> value;
+  detail::ForEachQueryParameterValue(resource, name, [&](size_t start, size_t end) {
+    value = std::string(resource.substr(start, end - start));
+    return false;
+  });
+  return value;
+}
+
+// Returns the request resource with the values of the given query parameter masked, so that it
+// can be logged without leaking secrets
+inline std::string RedactQueryParameter(std::string_view resource, std::string_view name) {
+  std::string result;
+  result.reserve(resource.size());
+  size_t copied = 0;
+  detail::ForEachQueryParameterValue(resource, name, [&](size_t start, size_t end) {
+    result.append(resource.substr(copied, start - copied));
+    result.append("***");
+    copied = end;
+    return true;
+  });
+  result.append(resource.substr(copied));
+  return result;
+}
+
+}  // namespace foxglove
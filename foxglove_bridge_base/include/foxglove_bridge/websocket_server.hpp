@@ -1,10 +1,12 @@
 #pragma once
 
 #include <algorithm>
+#include <cctype>
 #include <cstdint>
 #include <functional>
 #include <map>
 #include <memory>
+#include <mutex>
 #include <optional>
 #include <shared_mutex>
 #include <string_view>
@@ -16,7 +18,9 @@
 #include <nlohmann/json.hpp>
 
 #include "common.hpp"
+#include "foxglove_bridge.hpp"
 #include "serialization.hpp"
+#include "url.hpp"
 #include "websocket_logging.hpp"
 #include "websocket_notls.hpp"
 #include "websocket_tls.hpp"
@@ -33,6 +37,28 @@ static const websocketpp::log::level WARNING = websocketpp::log::elevel::warn;
 static const websocketpp::log::level RECOVERABLE = websocketpp::log::elevel::rerror;
 
 constexpr size_t DEFAULT_SEND_BUFFER_LIMIT_BYTES = 10000000UL;  // 10 MB
+constexpr char AUTH_TOKEN_QUERY_PARAMETER[] = "token";
+
+// Validates the token a client presented in the handshake. Returns the client's identity (which
+// may be empty) to accept the connection, or std::nullopt to reject it
+using AuthHandler = std::function<std::optional<std::string>(const std::string&, ConnHandle)>;
+
+// Inspects the certificate a client presented in the TLS handshake, after it was verified against
+// the server's CA. Returns the client's identity to accept the connection, or std::nullopt to
+// reject it
+using ClientCertificateHandler = std::function<std::optional<std::string>(X509*, ConnHandle)>;
+
+// Returns an AuthHandler accepting only clients that present the given token. The token is compared
+// in constant time and accepted clients have an empty identity
+inline AuthHandler MakeTokenAuthHandler(std::string expectedToken) {
+  return [expectedToken = std::move(expectedToken)](
+           const std::string& token, ConnHandle) -> std::optional<std::string> {
+    if (ConstantTimeEquals(token, expectedToken)) {
+      return std::string{};
+    }
+    return std::nullopt;
+  };
+}
 
 constexpr uint32_t Integer(const std::string_view str) {
   uint32_t result = 0x811C9DC5;  // FNV-1a 32-bit algorithm
@@ -143,6 +169,8 @@ public:
   virtual void setClientAdvertiseHandler(ClientAdvertiseHandler handler) = 0;
   virtual void setClientUnadvertiseHandler(ClientUnadvertiseHandler handler) = 0;
   virtual void setClientMessageHandler(ClientMessageHandler handler) = 0;
+  virtual void setAuthHandler(AuthHandler handler) = 0;
+  virtual void setClientCertificateHandler(ClientCertificateHandler handler) = 0;
 
   virtual void sendMessage(ConnHandle clientHandle, ChannelId chanId, uint64_t timestamp,
                            std::string_view data) = 0;
@@ -150,6 +178,7 @@ public:
 
   virtual std::optional<Tcp::endpoint> localEndpoint() = 0;
   virtual std::string remoteEndpointString(ConnHandle clientHandle) = 0;
+  virtual std::string clientIdentity(ConnHandle clientHandle) = 0;
 
 private:
   virtual void setupTlsHandler() = 0;
@@ -172,7 +201,8 @@ public:
   explicit Server(std::string name, LogCallback logger,
                   const std::vector<std::string>& capabilities,
                   size_t send_buffer_limit_bytes = DEFAULT_SEND_BUFFER_LIMIT_BYTES,
-                  const std::string& certfile = "", const std::string& keyfile = "");
+                  const std::string& certfile = "", const std::string& keyfile = "",
+                  const std::string& cafile = "");
   virtual ~Server();
 
   Server(const Server&) = delete;
@@ -192,6 +222,8 @@ public:
   void setClientAdvertiseHandler(ClientAdvertiseHandler handler) override;
   void setClientUnadvertiseHandler(ClientUnadvertiseHandler handler) override;
   void setClientMessageHandler(ClientMessageHandler handler) override;
+  void setAuthHandler(AuthHandler handler) override;
+  void setClientCertificateHandler(ClientCertificateHandler handler) override;
 
   void sendMessage(ConnHandle clientHandle, ChannelId chanId, uint64_t timestamp,
                    std::string_view data) override;
@@ -199,6 +231,7 @@ public:
 
   std::optional<Tcp::endpoint> localEndpoint() override;
   std::string remoteEndpointString(ConnHandle clientHandle) override;
+  std::string clientIdentity(ConnHandle clientHandle) override;
 
 private:
   struct ClientInfo {
@@ -220,6 +253,7 @@ private:
   size_t _send_buffer_limit_bytes;
   std::string _certfile;
   std::string _keyfile;
+  std::string _cafile;
   ServerType _server;
   std::unique_ptr<std::thread> _serverThread;
 
@@ -233,13 +267,22 @@ private:
   ClientAdvertiseHandler _clientAdvertiseHandler;
   ClientUnadvertiseHandler _clientUnadvertiseHandler;
   ClientMessageHandler _clientMessageHandler;
+  AuthHandler _authHandler;
+  ClientCertificateHandler _clientCertificateHandler;
   std::shared_mutex _clientsChannelMutex;
+  // Guarded by its own mutex so that handlers invoked under _clientsChannelMutex can look up
+  // client identities
+  std::map<ConnHandle, std::string, std::owner_less<>> _clientIdentities;
+  std::mutex _clientIdentitiesMutex;
 
   void setupTlsHandler() override;
   void socketInit(ConnHandle hdl);
   bool validateConnection(ConnHandle hdl);
+  bool validateClientCertificate(ConnHandle hdl);
+  std::string authToken(ConnHandle hdl);
   void handleConnectionOpened(ConnHandle hdl);
   void handleConnectionClosed(ConnHandle hdl);
+  void handleConnectionFailed(ConnHandle hdl);
   void handleMessage(ConnHandle hdl, MessagePtr msg);
   void handleTextMessage(ConnHandle hdl, const std::string& msg);
   void handleBinaryMessage(ConnHandle hdl, const uint8_t* msg, size_t length);
@@ -254,13 +297,15 @@ template <typename ServerConfiguration>
 inline Server<ServerConfiguration>::Server(std::string name, LogCallback logger,
                                            const std::vector<std::string>& capabilities,
                                            size_t send_buffer_limit_bytes,
-                                           const std::string& certfile, const std::string& keyfile)
+                                           const std::string& certfile, const std::string& keyfile,
+                                           const std::string& cafile)
     : _name(std::move(name))
     , _logger(logger)
     , _capabilities(capabilities)
     , _send_buffer_limit_bytes(send_buffer_limit_bytes)
     , _certfile(certfile)
-    , _keyfile(keyfile) {
+    , _keyfile(keyfile)
+    , _cafile(cafile) {
   // Redirect logging
   _server.get_alog().set_callback(_logger);
   _server.get_elog().set_callback(_logger);
@@ -279,6 +324,7 @@ inline Server<ServerConfiguration>::Server(std::string name, LogCallback logger,
   _server.set_open_handler(std::bind(&Server::handleConnectionOpened, this, std::placeholders::_1));
   _server.set_close_handler(
     std::bind(&Server::handleConnectionClosed, this, std::placeholders::_1));
+  _server.set_fail_handler(std::bind(&Server::handleConnectionFailed, this, std::placeholders::_1));
   _server.set_message_handler(
     std::bind(&Server::handleMessage, this, std::placeholders::_1, std::placeholders::_2));
   _server.set_reuse_addr(true);
@@ -302,24 +348,75 @@ inline bool Server<ServerConfiguration>::validateConnection(ConnHandle hdl) {
   auto con = _server.get_con_from_hdl(hdl);
 
   const auto& subprotocols = con->get_requested_subprotocols();
-  if (std::find(subprotocols.begin(), subprotocols.end(), SUPPORTED_SUBPROTOCOL) !=
+  if (std::find(subprotocols.begin(), subprotocols.end(), SUPPORTED_SUBPROTOCOL) ==
       subprotocols.end()) {
-    con->select_subprotocol(SUPPORTED_SUBPROTOCOL);
-    return true;
+    _server.get_alog().write(APP, "Rejecting client " + remoteEndpointString(hdl) +
+                                    " which did not declare support for subprotocol " +
+                                    SUPPORTED_SUBPROTOCOL);
+    return false;
+  }
+
+  if (!validateClientCertificate(hdl)) {
+    con->set_status(websocketpp::http::status_code::forbidden);
+    return false;
+  }
+
+  if (_authHandler) {
+    auto identity = _authHandler(authToken(hdl), hdl);
+    if (!identity) {
+      _server.get_alog().write(APP, "Rejecting client " + remoteEndpointString(hdl) +
+                                      " which failed authentication");
+      con->set_status(websocketpp::http::status_code::unauthorized);
+      con->append_header("WWW-Authenticate", "Bearer");
+      return false;
+    }
+    // An empty identity keeps the one taken from the client certificate, if any
+    if (!identity->empty()) {
+      std::lock_guard<std::mutex> lock(_clientIdentitiesMutex);
+      _clientIdentities.insert_or_assign(hdl, std::move(*identity));
+    }
+  }
+
+  con->select_subprotocol(SUPPORTED_SUBPROTOCOL);
+  return true;
+}
+
+template <typename ServerConfiguration>
+inline std::string Server<ServerConfiguration>::authToken(ConnHandle hdl) {
+  auto con = _server.get_con_from_hdl(hdl);
+
+  // Prefer an "Authorization: Bearer <token>" header. The scheme is case-insensitive
+  constexpr std::string_view BEARER_SCHEME = "bearer ";
+  const auto& authorization = con->get_request_header("Authorization");
+  if (authorization.size() > BEARER_SCHEME.size() &&
+      std::equal(BEARER_SCHEME.begin(), BEARER_SCHEME.end(), authorization.begin(),
+                 [](char a, char b) {
+                   return a == std::tolower(static_cast<unsigned char>(b));
+                 })) {
+    const auto tokenStart = authorization.find_first_not_of(' ', BEARER_SCHEME.size());
+    if (tokenStart != std::string::npos) {
+      return authorization.substr(tokenStart);
+    }
   }
-  _server.get_alog().write(APP, "Rejecting client " + remoteEndpointString(hdl) +
-                                  " which did not declare support for subprotocol " +
-                                  SUPPORTED_SUBPROTOCOL);
-  return false;
+
+  // Browsers cannot set custom handshake headers, so also accept a query parameter
+  if (const auto token = GetQueryParameter(con->get_resource(), AUTH_TOKEN_QUERY_PARAMETER)) {
+    return PercentDecode(*token);
+  }
+  return "";
 }
 
 template <typename ServerConfiguration>
 inline void Server<ServerConfiguration>::handleConnectionOpened(ConnHandle hdl) {
   std::unique_lock<std::shared_mutex> lock(_clientsChannelMutex);
   auto con = _server.get_con_from_hdl(hdl);
+  const auto identity = clientIdentity(hdl);
   const auto endpoint = remoteEndpointString(hdl);
-  _server.get_alog().write(APP, "Client " + endpoint + " connected via " + con->get_resource());
-  _clients.emplace(hdl, ClientInfo{endpoint, hdl, {}, {}});
+  const auto clientName = identity.empty() ? endpoint : identity + "@" + endpoint;
+  _server.get_alog().write(
+    APP, "Client " + clientName + " connected via " +
+           RedactQueryParameter(con->get_resource(), AUTH_TOKEN_QUERY_PARAMETER));
+  _clients.emplace(hdl, ClientInfo{clientName, hdl, {}, {}});
 
   con->send(json({
                    {"op", "serverInfo"},
@@ -362,6 +459,11 @@ inline void Server<ServerConfiguration>::handleConnectionClosed(ConnHandle hdl)
     _clients.erase(clientIt);
   }
 
+  {
+    std::lock_guard<std::mutex> lock(_clientIdentitiesMutex);
+    _clientIdentities.erase(hdl);
+  }
+
   // Unadvertise all channels this client advertised
   for (const auto clientChannelId : oldAdvertisedChannels) {
     _server.get_alog().write(APP, "Client " + clientName + " unadvertising channel " +
@@ -382,6 +484,13 @@ inline void Server<ServerConfiguration>::handleConnectionClosed(ConnHandle hdl)
   }
 }
 
+template <typename ServerConfiguration>
+inline void Server<ServerConfiguration>::handleConnectionFailed(ConnHandle hdl) {
+  // The handshake may fail after the connection was validated (and its identity stored)
+  std::lock_guard<std::mutex> lock(_clientIdentitiesMutex);
+  _clientIdentities.erase(hdl);
+}
+
 template <typename ServerConfiguration>
 inline void Server<ServerConfiguration>::setSubscribeHandler(SubscribeUnsubscribeHandler handler) {
   _subscribeHandler = std::move(handler);
@@ -409,6 +518,21 @@ inline void Server<ServerConfiguration>::setClientMessageHandler(ClientMessageHa
   _clientMessageHandler = std::move(handler);
 }
 
+template <typename ServerConfiguration>
+inline void Server<ServerConfiguration>::setAuthHandler(AuthHandler handler) {
+  _authHandler = std::move(handler);
+}
+
+template <typename ServerConfiguration>
+inline void Server<ServerConfiguration>::setClientCertificateHandler(
+  ClientCertificateHandler handler) {
+  // Without a CA, clients are not asked for certificates and the handler would never run
+  if (_cafile.empty()) {
+    throw std::runtime_error("A client certificate handler requires a CA file");
+  }
+  _clientCertificateHandler = std::move(handler);
+}
+
 template <typename ServerConfiguration>
 inline void Server<ServerConfiguration>::stop() {
   if (_server.stopped()) {
@@ -873,6 +997,13 @@ inline std::string Server<ServerConfiguration>::remoteEndpointString(ConnHandle
   return con ? con->get_remote_endpoint() : "(unknown)";
 }
 
+template <typename ServerConfiguration>
+inline std::string Server<ServerConfiguration>::clientIdentity(ConnHandle clientHandle) {
+  std::lock_guard<std::mutex> lock(_clientIdentitiesMutex);
+  const auto it = _clientIdentities.find(clientHandle);
+  return it != _clientIdentities.end() ? it->second : "";
+}
+
 template <>
 bool Server<WebSocketNoTls>::USES_TLS = false;
 
@@ -881,11 +1012,61 @@ bool Server<WebSocketTls>::USES_TLS = true;
 
 template <>
 inline void Server<WebSocketNoTls>::setupTlsHandler() {
+  if (!_cafile.empty()) {
+    throw std::runtime_error("Client certificate verification (" + _cafile +
+                             ") requires TLS to be enabled");
+  }
   _server.get_alog().write(APP, "Server running without TLS");
 }
 
+template <>
+inline bool Server<WebSocketNoTls>::validateClientCertificate(ConnHandle hdl) {
+  (void)hdl;
+  return true;
+}
+
+template <>
+inline bool Server<WebSocketTls>::validateClientCertificate(ConnHandle hdl) {
+  if (_cafile.empty()) {
+    return true;
+  }
+
+  auto con = _server.get_con_from_hdl(hdl);
+  const std::unique_ptr<X509, decltype(&X509_free)> certificate(
+    SSL_get_peer_certificate(con->get_socket().native_handle()), &X509_free);
+  if (!certificate) {
+    _server.get_alog().write(APP, "Rejecting client " + remoteEndpointString(hdl) +
+                                    " which did not present a certificate");
+    return false;
+  }
+
+  auto identity = _clientCertificateHandler
+                    ? _clientCertificateHandler(certificate.get(), hdl)
+                    : std::optional<std::string>(CertificateCommonName(certificate.get()));
+  if (!identity) {
+    _server.get_alog().write(APP, "Rejecting client " + remoteEndpointString(hdl) +
+                                    " whose certificate was rejected");
+    return false;
+  }
+
+  std::lock_guard<std::mutex> lock(_clientIdentitiesMutex);
+  _clientIdentities.insert_or_assign(hdl, std::move(*identity));
+  return true;
+}
+
 template <>
 inline void Server<WebSocketTls>::setupTlsHandler() {
+  // Fail early instead of accepting clients without verifying their certificates
+  if (!_cafile.empty()) {
+    namespace asio = websocketpp::lib::asio;
+    asio::ssl::context verifyCtx(asio::ssl::context::sslv23);
+    std::error_code ec;
+    verifyCtx.load_verify_file(_cafile, ec);
+    if (ec) {
+      throw std::runtime_error("Failed to load CA file " + _cafile + ": " + ec.message());
+    }
+  }
+
   _server.set_tls_init_handler([this](ConnHandle hdl) {
     (void)hdl;
 
@@ -898,6 +1079,13 @@ inline void Server<WebSocketTls>::setupTlsHandler() {
       ctx->use_certificate_chain_file(_certfile);
       ctx->use_private_key_file(_keyfile, asio::ssl::context::pem);
 
+      // Require clients to present a certificate signed by the given CA (mutual TLS). The verify
+      // mode is set first so that a failure to load the CA rejects every handshake
+      if (!_cafile.empty()) {
+        ctx->set_verify_mode(asio::ssl::verify_peer | asio::ssl::verify_fail_if_no_peer_cert);
+        ctx->load_verify_file(_cafile);
+      }
+
       // Ciphers are taken from the websocketpp example echo tls server:
       // https://github.com/zaphoyd/websocketpp/blob/1b11fd301/examples/echo_server_tls/echo_server_tls.cpp#L119
       constexpr char ciphers[] =
@@ -1,5 +1,8 @@
 #pragma once
 
+#include <string>
+
+#include <openssl/x509.h>
 #include <websocketpp/config/asio.hpp>
 
 #include "./websocket_logging.hpp"
@@ -36,4 +39,23 @@ struct WebSocketTls : public websocketpp::config::core {
   typedef websocketpp::transport::asio::endpoint<transport_config> transport_type;
 };
 
+// Returns the common name (CN) of a certificate's subject, or an empty string if it has none
+inline std::string CertificateCommonName(X509* certificate) {
+  X509_NAME* subject = X509_get_subject_name(certificate);
+  const int index = X509_NAME_get_index_by_NID(subject, NID_commonName, -1);
+  if (index < 0) {
+    return "";
+  }
+
+  unsigned char* utf8 = nullptr;
+  const int length =
+    ASN1_STRING_to_UTF8(&utf8, X509_NAME_ENTRY_get_data(X509_NAME_get_entry(subject, index)));
+  if (length < 0) {
+    return "";
+  }
+  std::string commonName(reinterpret_cast<char*>(utf8), static_cast<size_t>(length));
+  OPENSSL_free(utf8);
+  return commonName;
+}
+
 }  // namespace foxglove
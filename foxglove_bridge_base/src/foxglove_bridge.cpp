@@ -1,5 +1,11 @@
 #include "foxglove_bridge/foxglove_bridge.hpp"
 
+#include <fstream>
+#include <iterator>
+#include <stdexcept>
+
+#include <openssl/crypto.h>
+
 #define ASIO_STANDALONE
 #include "foxglove_bridge/websocket_notls.hpp"
 #include "foxglove_bridge/websocket_server.hpp"
@@ -11,6 +17,32 @@ const char* WebSocketUserAgent() {
   return websocketpp::user_agent;
 }
 
+bool ConstantTimeEquals(std::string_view a, std::string_view b) {
+  if (a.size() != b.size()) {
+    return false;
+  }
+  return CRYPTO_memcmp(a.data(), b.data(), a.size()) == 0;
+}
+
+std::string ResolveAuthToken(const std::string& token, const std::string& tokenFile) {
+  if (tokenFile.empty()) {
+    return token;
+  } else if (!token.empty()) {
+    throw std::runtime_error("Only one of \"auth_token\" and \"auth_token_file\" may be set");
+  }
+
+  std::ifstream file(tokenFile);
+  if (!file) {
+    throw std::runtime_error("Failed to read authentication token from " + tokenFile);
+  }
+  std::string fileToken{std::istreambuf_iterator<char>(file), std::istreambuf_iterator<char>()};
+  fileToken.erase(fileToken.find_last_not_of(" \t\r\n") + 1);
+  if (fileToken.empty()) {
+    throw std::runtime_error("Authentication token file " + tokenFile + " is empty");
+  }
+  return fileToken;
+}
+
 // Explicit template instantiation for common server configurations
 template class Server<WebSocketTls>;
 template class Server<WebSocketNoTls>;
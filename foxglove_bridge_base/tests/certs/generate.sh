@@ -0,0 +1,28 @@
+#!/usr/bin/env bash
+# Regenerates the certificates used by websocket_server_test. They are valid for 100 years
+set -euo pipefail
+cd "$(dirname "$0")"
+
+gen_ca() {
+  openssl req -x509 -newkey rsa:2048 -nodes -keyout "$1.key" -out "$1.crt" -days 36500 \
+    -subj "/CN=$2" -addext "basicConstraints=critical,CA:TRUE" \
+    -addext "keyUsage=critical,keyCertSign,cRLSign"
+}
+
+gen_cert() {
+  openssl req -newkey rsa:2048 -nodes -keyout "$1.key" -out "$1.csr" -subj "/CN=$2"
+  printf "%b" "$4" > "$1.ext"
+  openssl x509 -req -in "$1.csr" -CA "$3.crt" -CAkey "$3.key" -CAcreateserial -out "$1.crt" \
+    -days 36500 -extfile "$1.ext"
+  rm "$1.csr" "$1.ext"
+}
+
+gen_ca ca "foxglove_bridge test CA"
+gen_ca other_ca "foxglove_bridge other test CA"
+gen_cert server localhost ca \
+  "basicConstraints=CA:FALSE\nsubjectAltName=DNS:localhost,IP:127.0.0.1\nextendedKeyUsage=serverAuth\n"
+gen_cert client alice ca "basicConstraints=CA:FALSE\nextendedKeyUsage=clientAuth\n"
+gen_cert other_client mallory other_ca "basicConstraints=CA:FALSE\nextendedKeyUsage=clientAuth\n"
+
+# Only the leaf certificates and the trusted CA certificate are needed by the tests
+rm -f ./*.srl ca.key other_ca.key other_ca.crt
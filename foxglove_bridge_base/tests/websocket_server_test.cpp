@@ -0,0 +1,273 @@
+#define ASIO_STANDALONE
+
+#include <chrono>
+#include <functional>
+#include <future>
+#include <memory>
+#include <optional>
+#include <string>
+#include <thread>
+#include <vector>
+
+#include <gtest/gtest.h>
+#include <websocketpp/client.hpp>
+#include <websocketpp/config/asio_client.hpp>
+
+#include <foxglove_bridge/url.hpp>
+#include <foxglove_bridge/websocket_client.hpp>
+#include <foxglove_bridge/websocket_server.hpp>
+
+using StatusCode = websocketpp::http::status_code::value;
+
+// Contains characters that have to be percent-encoded in a query parameter
+constexpr char AUTH_TOKEN[] = "s3cr3t+/=";
+constexpr char AUTH_TOKEN_URL_ENCODED[] = "s3cr3t%2B%2F%3D";
+
+// Performs a websocket handshake and returns the HTTP status code of the server's response. The
+// optional configure callback is invoked on the client before connecting
+template <typename ClientConfiguration = websocketpp::config::asio_client>
+StatusCode connectAndGetStatus(
+  const std::string& uri, const std::string& authorization = "",
+  std::function<void(websocketpp::client<ClientConfiguration>&)> configure = nullptr) {
+  using ClientType = websocketpp::client<ClientConfiguration>;
+  ClientType client;
+  client.clear_access_channels(websocketpp::log::alevel::all);
+  client.clear_error_channels(websocketpp::log::elevel::all);
+  client.init_asio();
+  if (configure) {
+    configure(client);
+  }
+
+  std::promise<StatusCode> statusPromise;
+  auto statusFuture = statusPromise.get_future();
+
+  websocketpp::lib::error_code ec;
+  auto con = client.get_connection(uri, ec);
+  if (ec) {
+    throw std::runtime_error("Failed to get connection from URI " + uri);
+  }
+  con->add_subprotocol(foxglove::SUPPORTED_SUBPROTOCOL);
+  if (!authorization.empty()) {
+    con->append_header("Authorization", authorization);
+  }
+  con->set_open_handler([&](websocketpp::connection_hdl hdl) {
+    statusPromise.set_value(client.get_con_from_hdl(hdl)->get_response_code());
+    client.close(hdl, websocketpp::close::status::going_away, "");
+  });
+  con->set_fail_handler([&](websocketpp::connection_hdl hdl) {
+    statusPromise.set_value(client.get_con_from_hdl(hdl)->get_response_code());
+  });
+  client.connect(con);
+
+  std::thread clientThread([&client]() {
+    client.run();
+  });
+  const auto status = statusFuture.wait_for(std::chrono::seconds(5)) == std::future_status::ready
+                        ? statusFuture.get()
+                        : websocketpp::http::status_code::uninitialized;
+  client.stop();
+  clientThread.join();
+  return status;
+}
+
+class TokenAuthTest : public ::testing::Test {
+protected:
+  void SetUp() override {
+    _server = std::make_unique<foxglove::Server<foxglove::WebSocketNoTls>>(
+      "test", foxglove::NoOpLogCallback, std::vector<std::string>{});
+    _server->setAuthHandler(foxglove::MakeTokenAuthHandler(AUTH_TOKEN));
+    _server->start("127.0.0.1", 0);
+    _uri = "ws://127.0.0.1:" + std::to_string(_server->localEndpoint()->port()) + "/";
+  }
+
+  void TearDown() override {
+    _server->stop();
+  }
+
+  std::unique_ptr<foxglove::ServerInterface> _server;
+  std::string _uri;
+};
+
+TEST_F(TokenAuthTest, rejectsMissingToken) {
+  EXPECT_EQ(websocketpp::http::status_code::unauthorized, connectAndGetStatus(_uri));
+}
+
+TEST_F(TokenAuthTest, rejectsWrongToken) {
+  EXPECT_EQ(websocketpp::http::status_code::unauthorized,
+            connectAndGetStatus(_uri, "Bearer wrong"));
+  EXPECT_EQ(websocketpp::http::status_code::unauthorized,
+            connectAndGetStatus(_uri + "?token=wrong"));
+  // The query parameter must be percent-decoded before comparing
+  EXPECT_EQ(websocketpp::http::status_code::unauthorized,
+            connectAndGetStatus(_uri + "?token=s3cr3t"));
+}
+
+TEST_F(TokenAuthTest, acceptsBearerHeader) {
+  EXPECT_EQ(websocketpp::http::status_code::switching_protocols,
+            connectAndGetStatus(_uri, std::string("Bearer ") + AUTH_TOKEN));
+  EXPECT_EQ(websocketpp::http::status_code::switching_protocols,
+            connectAndGetStatus(_uri, std::string("bearer ") + AUTH_TOKEN));
+}
+
+TEST_F(TokenAuthTest, acceptsQueryParameter) {
+  EXPECT_EQ(websocketpp::http::status_code::switching_protocols,
+            connectAndGetStatus(_uri + "?token=" + AUTH_TOKEN_URL_ENCODED));
+  EXPECT_EQ(websocketpp::http::status_code::switching_protocols,
+            connectAndGetStatus(_uri + "?foo=bar&token=" + AUTH_TOKEN_URL_ENCODED));
+  // An empty bearer header must not hide the query parameter
+  EXPECT_EQ(websocketpp::http::status_code::switching_protocols,
+            connectAndGetStatus(_uri + "?token=" + AUTH_TOKEN_URL_ENCODED, "Bearer "));
+}
+
+class ClientIdentityTest : public ::testing::Test {
+protected:
+  void SetUp() override {
+    _server = std::make_unique<foxglove::Server<foxglove::WebSocketNoTls>>(
+      "test", foxglove::NoOpLogCallback, std::vector<std::string>{});
+    _server->setAuthHandler(
+      [this](const std::string& token, foxglove::ConnHandle hdl) -> std::optional<std::string> {
+        if (token != "alice-token") {
+          return std::nullopt;
+        }
+        _handlePromise.set_value(hdl);
+        return "alice";
+      });
+    _server->start("127.0.0.1", 0);
+    _uri = "ws://127.0.0.1:" + std::to_string(_server->localEndpoint()->port()) + "/";
+  }
+
+  void TearDown() override {
+    _server->stop();
+  }
+
+  std::unique_ptr<foxglove::ServerInterface> _server;
+  std::promise<foxglove::ConnHandle> _handlePromise;
+  std::string _uri;
+};
+
+TEST_F(ClientIdentityTest, storesIdentityWhileConnected) {
+  auto handleFuture = _handlePromise.get_future();
+  auto client = std::make_unique<foxglove::Client<websocketpp::config::asio_client>>();
+  ASSERT_EQ(std::future_status::ready,
+            client->connect(_uri + "?token=alice-token").wait_for(std::chrono::seconds(5)));
+  ASSERT_EQ(std::future_status::ready, handleFuture.wait_for(std::chrono::seconds(5)));
+  const auto hdl = handleFuture.get();
+  EXPECT_EQ("alice", _server->clientIdentity(hdl));
+
+  // The identity is removed once the server has processed the disconnect
+  client.reset();
+  const auto deadline = std::chrono::steady_clock::now() + std::chrono::seconds(5);
+  while (!_server->clientIdentity(hdl).empty() && std::chrono::steady_clock::now() < deadline) {
+    std::this_thread::sleep_for(std::chrono::milliseconds(10));
+  }
+  EXPECT_EQ("", _server->clientIdentity(hdl));
+}
+
+TEST(ClientCertificateTest, refusesUnloadableCaFile) {
+  // Must fail closed rather than accepting clients without verifying their certificates
+  EXPECT_THROW(foxglove::Server<foxglove::WebSocketTls>(
+                 "test", foxglove::NoOpLogCallback, std::vector<std::string>{},
+                 foxglove::DEFAULT_SEND_BUFFER_LIMIT_BYTES, "", "", "/nonexistent/ca.pem"),
+               std::runtime_error);
+}
+
+TEST(ClientCertificateTest, refusesCaFileWithoutTls) {
+  EXPECT_THROW(foxglove::Server<foxglove::WebSocketNoTls>(
+                 "test", foxglove::NoOpLogCallback, std::vector<std::string>{},
+                 foxglove::DEFAULT_SEND_BUFFER_LIMIT_BYTES, "", "", "/etc/ssl/certs/ca.pem"),
+               std::runtime_error);
+}
+
+// Configures a TLS client to present the given certificate, or no certificate if empty
+std::function<void(websocketpp::client<websocketpp::config::asio_tls_client>&)>
+useClientCertificate(const std::string& name) {
+  return [name](websocketpp::client<websocketpp::config::asio_tls_client>& client) {
+    client.set_tls_init_handler([name](websocketpp::connection_hdl) {
+      namespace asio = websocketpp::lib::asio;
+      auto ctx = websocketpp::lib::make_shared<asio::ssl::context>(asio::ssl::context::sslv23);
+      if (!name.empty()) {
+        ctx->use_certificate_chain_file(std::string(TEST_CERTS_DIR) + "/" + name + ".crt");
+        ctx->use_private_key_file(std::string(TEST_CERTS_DIR) + "/" + name + ".key",
+                                  asio::ssl::context::pem);
+      }
+      return ctx;
+    });
+  };
+}
+
+class MutualTlsTest : public ::testing::Test {
+protected:
+  void SetUp() override {
+    _server = std::make_unique<foxglove::Server<foxglove::WebSocketTls>>(
+      "test", foxglove::NoOpLogCallback, std::vector<std::string>{},
+      foxglove::DEFAULT_SEND_BUFFER_LIMIT_BYTES, std::string(TEST_CERTS_DIR) + "/server.crt",
+      std::string(TEST_CERTS_DIR) + "/server.key", std::string(TEST_CERTS_DIR) + "/ca.crt");
+  }
+
+  void TearDown() override {
+    _server->stop();
+  }
+
+  StatusCode connectWithCertificate(const std::string& clientCertificate) {
+    if (_uri.empty()) {
+      _server->start("127.0.0.1", 0);
+      _uri = "wss://127.0.0.1:" + std::to_string(_server->localEndpoint()->port()) + "/";
+    }
+    return connectAndGetStatus<websocketpp::config::asio_tls_client>(
+      _uri, "", useClientCertificate(clientCertificate));
+  }
+
+  std::unique_ptr<foxglove::ServerInterface> _server;
+  std::string _uri;
+};
+
+TEST_F(MutualTlsTest, rejectsClientWithoutCertificate) {
+  EXPECT_NE(websocketpp::http::status_code::switching_protocols, connectWithCertificate(""));
+}
+
+TEST_F(MutualTlsTest, rejectsCertificateFromOtherCa) {
+  EXPECT_NE(websocketpp::http::status_code::switching_protocols,
+            connectWithCertificate("other_client"));
+}
+
+TEST_F(MutualTlsTest, acceptsSignedCertificate) {
+  EXPECT_EQ(websocketpp::http::status_code::switching_protocols, connectWithCertificate("client"));
+}
+
+TEST_F(MutualTlsTest, certificateHandlerDecides) {
+  std::vector<std::string> commonNames;
+  bool accept = true;
+  _server->setClientCertificateHandler(
+    [&](X509* certificate, foxglove::ConnHandle) -> std::optional<std::string> {
+      commonNames.push_back(foxglove::CertificateCommonName(certificate));
+      return accept ? std::make_optional(commonNames.back()) : std::nullopt;
+    });
+
+  EXPECT_EQ(websocketpp::http::status_code::switching_protocols, connectWithCertificate("client"));
+  accept = false;
+  EXPECT_EQ(websocketpp::http::status_code::forbidden, connectWithCertificate("client"));
+  EXPECT_EQ((std::vector<std::string>{"alice", "alice"}), commonNames);
+}
+
+TEST(QueryParameterTest, redactsToken) {
+  EXPECT_EQ("/?foo=bar&token=***&baz=1",
+            foxglove::RedactQueryParameter("/?foo=bar&token=secret&baz=1", "token"));
+  EXPECT_EQ("/?tokens=abc", foxglove::RedactQueryParameter("/?tokens=abc", "token"));
+  EXPECT_EQ("/?token=***&token=***", foxglove::RedactQueryParameter("/?token=a&token=b", "token"));
+}
+
+TEST(QueryParameterTest, findsFirstValue) {
+  EXPECT_EQ("a%2B", foxglove::GetQueryParameter("/?foo=bar&token=a%2B&token=b", "token"));
+  EXPECT_EQ(std::nullopt, foxglove::GetQueryParameter("/?tokens=abc", "token"));
+  EXPECT_EQ(std::nullopt, foxglove::GetQueryParameter("/token=abc", "token"));
+}
+
+TEST(QueryParameterTest, percentDecodes) {
+  EXPECT_EQ("x+y", foxglove::PercentDecode("x%2By"));
+  EXPECT_EQ("100%", foxglove::PercentDecode("100%"));
+}
+
+int main(int argc, char** argv) {
+  testing::InitGoogleTest(&argc, argv);
+  return RUN_ALL_TESTS();
+}
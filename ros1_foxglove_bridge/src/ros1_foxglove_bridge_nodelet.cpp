@@ -46,6 +46,9 @@ public:
     const auto useTLS = nhp.param<bool>("tls", false);
     const auto certfile = nhp.param<std::string>("certfile", "");
     const auto keyfile = nhp.param<std::string>("keyfile", "");
+    const auto cafile = nhp.param<std::string>("cafile", "");
+    const auto authToken = nhp.param<std::string>("auth_token", "");
+    const auto authTokenFile = nhp.param<std::string>("auth_token_file", "");
     _maxUpdateMs = static_cast<size_t>(nhp.param<int>("max_update_ms", DEFAULT_MAX_UPDATE_MS));
     _useSimTime = nhp.param<bool>("/use_sim_time", false);
 
@@ -76,10 +79,11 @@ public:
       if (useTLS) {
         _server = std::make_unique<foxglove::Server<foxglove::WebSocketTls>>(
           "foxglove_bridge", std::move(logHandler), serverCapabilities, send_buffer_limit, certfile,
-          keyfile);
+          keyfile, cafile);
       } else {
         _server = std::make_unique<foxglove::Server<foxglove::WebSocketNoTls>>(
-          "foxglove_bridge", std::move(logHandler), serverCapabilities, send_buffer_limit);
+          "foxglove_bridge", std::move(logHandler), serverCapabilities, send_buffer_limit, certfile,
+          keyfile, cafile);
       }
 
       _server->setSubscribeHandler(std::bind(&FoxgloveBridge::subscribeHandler, this,
@@ -93,6 +97,10 @@ public:
                                                      std::placeholders::_2));
       _server->setClientMessageHandler(std::bind(&FoxgloveBridge::clientMessageHandler, this,
                                                  std::placeholders::_1, std::placeholders::_2));
+      if (const auto token = foxglove::ResolveAuthToken(authToken, authTokenFile);
+          !token.empty()) {
+        _server->setAuthHandler(foxglove::MakeTokenAuthHandler(token));
+      }
       _server->start(address, static_cast<uint16_t>(port));
 
       updateAdvertisedTopics(ros::TimerEvent());
@@ -78,6 +78,32 @@ public:
     keyfileDescription.read_only = true;
     this->declare_parameter("keyfile", "", keyfileDescription);
 
+    auto cafileDescription = rcl_interfaces::msg::ParameterDescriptor{};
+    cafileDescription.name = "cafile";
+    cafileDescription.type = rcl_interfaces::msg::ParameterType::PARAMETER_STRING;
+    cafileDescription.description =
+      "Path to the CA certificate used to verify client certificates (mutual TLS)";
+    cafileDescription.read_only = true;
+    this->declare_parameter("cafile", "", cafileDescription);
+
+    auto authTokenDescription = rcl_interfaces::msg::ParameterDescriptor{};
+    authTokenDescription.name = "auth_token";
+    authTokenDescription.type = rcl_interfaces::msg::ParameterType::PARAMETER_STRING;
+    authTokenDescription.description =
+      "Token clients must present to connect, either as a bearer token in the Authorization "
+      "header or as the \"token\" query parameter. Empty to allow all clients.";
+    authTokenDescription.read_only = true;
+    this->declare_parameter("auth_token", "", authTokenDescription);
+
+    auto authTokenFileDescription = rcl_interfaces::msg::ParameterDescriptor{};
+    authTokenFileDescription.name = "auth_token_file";
+    authTokenFileDescription.type = rcl_interfaces::msg::ParameterType::PARAMETER_STRING;
+    authTokenFileDescription.description =
+      "Path to a file containing the token clients must present to connect. Alternative to "
+      "\"auth_token\" that keeps the token out of the parameter server.";
+    authTokenFileDescription.read_only = true;
+    this->declare_parameter("auth_token_file", "", authTokenFileDescription);
+
     auto maxQosDepthDescription = rcl_interfaces::msg::ParameterDescriptor{};
     maxQosDepthDescription.name = "max_qos_depth";
     maxQosDepthDescription.type = rcl_interfaces::msg::ParameterType::PARAMETER_INTEGER;
@@ -131,6 +157,10 @@ public:
     const auto useTLS = this->get_parameter("tls").as_bool();
     const auto certfile = this->get_parameter("certfile").as_string();
     const auto keyfile = this->get_parameter("keyfile").as_string();
+    const auto cafile = this->get_parameter("cafile").as_string();
+    const auto authToken = foxglove::ResolveAuthToken(
+      this->get_parameter("auth_token").as_string(),
+      this->get_parameter("auth_token_file").as_string());
     _useSimTime = this->get_parameter("use_sim_time").as_bool();
     const auto logHandler = std::bind(&FoxgloveBridge::logHandler, this, _1, _2);
 
@@ -144,10 +174,11 @@ public:
     if (useTLS) {
       _server = std::make_unique<foxglove::Server<foxglove::WebSocketTls>>(
         "foxglove_bridge", std::move(logHandler), serverCapabilities, send_buffer_limit, certfile,
-        keyfile);
+        keyfile, cafile);
     } else {
       _server = std::make_unique<foxglove::Server<foxglove::WebSocketNoTls>>(
-        "foxglove_bridge", std::move(logHandler), serverCapabilities, send_buffer_limit);
+        "foxglove_bridge", std::move(logHandler), serverCapabilities, send_buffer_limit, certfile,
+        keyfile, cafile);
     }
     _server->setSubscribeHandler(std::bind(&FoxgloveBridge::subscribeHandler, this, _1, _2));
     _server->setUnsubscribeHandler(std::bind(&FoxgloveBridge::unsubscribeHandler, this, _1, _2));
@@ -157,6 +188,9 @@ public:
       std::bind(&FoxgloveBridge::clientUnadvertiseHandler, this, _1, _2));
     _server->setClientMessageHandler(
       std::bind(&FoxgloveBridge::clientMessageHandler, this, _1, _2));
+    if (!authToken.empty()) {
+      _server->setAuthHandler(foxglove::MakeTokenAuthHandler(authToken));
+    }
 
     auto address = this->get_parameter("address").as_string();
     uint16_t port = uint16_t(this->get_parameter("port").as_int());